@@ -4,17 +4,21 @@ use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use sha1::{Digest, Sha1};
+use sha2::Sha256;
 use std::ffi::CStr;
 use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 
+mod packfile;
+
 /// Git directories.
 const GIT_DIR: &str = ".git";
 const GIT_OBJECT_DIR: &str = ".git/objects";
 const GIT_REF_DIR: &str = ".git/refs";
 const GIT_HEAD: &str = ".git/HEAD";
+const GIT_CONFIG: &str = ".git/config";
 
 /// Application arguments.
 #[derive(Parser, Debug)]
@@ -28,7 +32,13 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Initialize a new git repository.
-    Init,
+    Init {
+        /// Object hash algorithm for this repository. SHA-256 is recorded as
+        /// `extensions.objectformat` in `.git/config`; SHA-1, the default,
+        /// writes no config.
+        #[clap(long, value_enum, default_value_t = ObjectFormat::Sha1)]
+        object_format: ObjectFormat,
+    },
 
     /// Cat file contents in object.
     CatFile {
@@ -44,21 +54,164 @@ enum Command {
 
         file_path: PathBuf,
     },
+
+    /// Walk the commit graph starting at `commit`, following `parent` links.
+    Log {
+        commit: String,
+
+        /// `--format=dot` emits a Graphviz `digraph` instead of the usual
+        /// textual history; any other value keeps the textual history.
+        #[clap(long)]
+        format: Option<String>,
+    },
+
+    /// Explode a packfile into loose objects in `.git/objects`.
+    UnpackObjects { pack_path: PathBuf },
+
+    /// Build a commit object from a tree and zero or more parent commits.
+    CommitTree {
+        tree: String,
+
+        #[clap(short = 'p')]
+        parents: Vec<String>,
+
+        #[clap(short = 'm')]
+        message: String,
+
+        /// Sign the commit with an SSH key (SSHSIG), like `git commit -S`
+        /// with `gpg.format=ssh` configured.
+        #[clap(long)]
+        sign: bool,
+
+        /// SSH private key to sign with. Required together with `--sign`.
+        #[clap(long)]
+        signing_key: Option<PathBuf>,
+    },
 }
 
 enum Kind {
     Blob,
+    Commit,
+    Tree,
+    Tag,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Blob => "blob",
+            Kind::Commit => "commit",
+            Kind::Tree => "tree",
+            Kind::Tag => "tag",
+        }
+    }
+}
+
+/// The object hash algorithm a repository is using, recorded at `init`
+/// time as `extensions.objectformat` in `.git/config`. Git defaults to
+/// SHA-1 for repositories that don't set this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    /// Width of a hex-encoded object id: 40 for SHA-1, 64 for SHA-256.
+    fn hex_len(self) -> usize {
+        match self {
+            ObjectFormat::Sha1 => 40,
+            ObjectFormat::Sha256 => 64,
+        }
+    }
+
+    /// Width of a raw (non-hex) object id, as embedded in tree entries
+    /// and REF_DELTA base ids: 20 bytes for SHA-1, 32 bytes for SHA-256.
+    pub(crate) fn raw_len(self) -> usize {
+        self.hex_len() / 2
+    }
+
+    /// The hash of the empty blob (`"blob 0\0"`), hardcoded per format so
+    /// it can be recognized without a corresponding object on disk.
+    fn empty_blob(self) -> &'static str {
+        match self {
+            ObjectFormat::Sha1 => "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391",
+            ObjectFormat::Sha256 => {
+                "473a0f4c3be8a93681a267e3b1e9a7dcda1185436fe141f7749120a303721813"
+            }
+        }
+    }
+
+    /// The hash of the empty tree (`"tree 0\0"`), hardcoded per format so
+    /// it can be recognized without a corresponding object on disk.
+    fn empty_tree(self) -> &'static str {
+        match self {
+            ObjectFormat::Sha1 => "4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            ObjectFormat::Sha256 => {
+                "6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321"
+            }
+        }
+    }
+}
+
+/// Read the object hash algorithm a repository was initialized with from
+/// `.git/config`. A missing config (or missing `objectformat` line) means
+/// SHA-1, matching git's own default.
+fn read_object_format() -> anyhow::Result<ObjectFormat> {
+    let Ok(config) = fs::read_to_string(GIT_CONFIG) else {
+        return Ok(ObjectFormat::Sha1);
+    };
+
+    if config.lines().any(|line| line.trim() == "objectformat = sha256") {
+        Ok(ObjectFormat::Sha256)
+    } else {
+        Ok(ObjectFormat::Sha1)
+    }
+}
+
+/// A SHA-1 or SHA-256 hasher behind one interface, so `HashWriter` doesn't
+/// need to be generic over the digest algorithm.
+enum AnyHasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl AnyHasher {
+    fn new(format: ObjectFormat) -> Self {
+        match format {
+            ObjectFormat::Sha1 => AnyHasher::Sha1(Sha1::new()),
+            ObjectFormat::Sha256 => AnyHasher::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            AnyHasher::Sha1(hasher) => Digest::update(hasher, data),
+            AnyHasher::Sha256(hasher) => Digest::update(hasher, data),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            AnyHasher::Sha1(hasher) => hex::encode(hasher.finalize()),
+            AnyHasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
     match args.command {
-        Command::Init => {
+        Command::Init { object_format } => {
             fs::create_dir(GIT_DIR).unwrap();
             fs::create_dir(format!("{GIT_OBJECT_DIR}")).unwrap();
             fs::create_dir(format!("{GIT_REF_DIR}")).unwrap();
             fs::write(format!("{GIT_HEAD}"), "ref: refs/heads/main\n").unwrap();
+            if object_format == ObjectFormat::Sha256 {
+                fs::write(GIT_CONFIG, "[extensions]\n\tobjectformat = sha256\n")
+                    .context("failed to write repository config")?;
+            }
             println!("Initialized git directory")
         }
         Command::CatFile {
@@ -69,65 +222,29 @@ fn main() -> anyhow::Result<()> {
                 pretty_print,
                 "mode '-p' should be give and we don't support other modes."
             );
-            anyhow::ensure!(
-                object_hash.len() == 40,
-                "object hash must be 40 characters long"
-            );
-            //TODO: support shortest unique object hash
-            let file = File::open(format!(
-                "{GIT_OBJECT_DIR}/{}/{}",
-                &object_hash[..2],
-                &object_hash[2..]
-            ))
-            .context(format!("Failed to open {GIT_OBJECT_DIR}"))?;
-
-            let zlib = ZlibDecoder::new(file);
-            let mut zlib = BufReader::new(zlib);
-            let mut buf = Vec::new();
-            zlib.read_until(0, &mut buf)
-                .context(format!("Failed to read header from {GIT_OBJECT_DIR}"))?;
-            let header = CStr::from_bytes_until_nul(&buf)
-                .expect("there is only one nul and that is at the end - this should not fail");
-            let header = header.to_str().context("header is valid utf-8")?;
-
-            let Some((kind, _)) = header.split_once(' ') else {
-                anyhow::bail!(
-                    "corrupted {GIT_OBJECT_DIR}! header doesn't start with a known known kind: '{header}'"
-                )
-            };
-
-            let kind = match kind {
-                "blob" => Kind::Blob,
-                _ => anyhow::bail!("kind {kind} is not implemented yet"),
-            };
-
-            let Some(size) = header.strip_prefix("blob ") else {
-                anyhow::bail!(
-                    "corrupted {GIT_OBJECT_DIR}! header doesn't start with 'blob ': '{header}'"
-                )
-            };
-            let size = size
-                .parse::<u64>()
-                .context("failed to parse size: {size}")?;
-
-            let mut zlib = LimitReader {
-                reader: zlib,
-                limit: size as usize,
-            };
+            let object_format = read_object_format()?;
+            let object_hash = resolve_hash(&object_hash, object_format)?;
+            let (kind, buf) = read_object(&object_hash, object_format)?;
 
             match kind {
                 Kind::Blob => {
-                    let n = std::io::copy(&mut zlib, &mut buf)
-                        .context("write .git/objects file to stdout")?;
-                    anyhow::ensure!(
-                        n == size,
-                        "{GIT_OBJECT_DIR} was not expected size (expected: {size} actual: {n}"
-                    );
+                    std::io::stdout()
+                        .write_all(&buf)
+                        .context("write blob contents to stdout")?;
                 }
+                Kind::Commit | Kind::Tag => {
+                    let body = std::str::from_utf8(&buf).context("commit/tag body is valid utf-8")?;
+                    print!("{body}");
+                }
+                Kind::Tree => print_tree(&buf, object_format)?,
             }
         }
         Command::HashObject { write, file_path } => {
-            fn write_blob<W: Write>(file: &Path, writer: W) -> anyhow::Result<String> {
+            fn write_blob<W: Write>(
+                file: &Path,
+                writer: W,
+                format: ObjectFormat,
+            ) -> anyhow::Result<String> {
                 let stat = fs::metadata(&file).with_context(|| {
                     format!(
                         "Failed to read metadata from {} - stat: {}",
@@ -139,7 +256,7 @@ fn main() -> anyhow::Result<()> {
                 let writer = ZlibEncoder::new(writer, Compression::default());
                 let mut writer = HashWriter {
                     writer,
-                    hasher: Sha1::new(),
+                    hasher: AnyHasher::new(format),
                 };
                 write!(writer, "blob")?;
                 write!(writer, "{}\0", stat.len())?;
@@ -149,28 +266,539 @@ fn main() -> anyhow::Result<()> {
                     .context("failed to copy file to encoder - stream file into blob")?;
 
                 let _ = writer.writer.finish()?;
-                let hash = writer.hasher.finalize();
 
-                Ok(hex::encode(hash))
+                Ok(writer.hasher.finalize_hex())
             }
 
+            let object_format = read_object_format()?;
+
             let hash = if write {
-                let tmp = "temporary";
+                // Write to a uniquely-named temp file inside .git/objects
+                // (not the CWD) so the final rename is on the same
+                // filesystem and never clobbers another writer's temp file.
+                let tmp_path = new_tmp_object_path();
                 let hash = write_blob(
                     &file_path,
-                    File::create(tmp).context("failed to construct temporary file for blob")?,
+                    File::create(&tmp_path).context("failed to construct temporary file for blob")?,
+                    object_format,
                 )?;
-                fs::rename(
-                    tmp,
-                    format!("{GIT_OBJECT_DIR}/{}/{}", &hash[..2], &hash[2..]),
-                )
-                .context("failed to move temporary file to object directory")?;
+                finish_object(&tmp_path, &hash)?;
                 hash
             } else {
-                write_blob(&file_path, std::io::sink())?
+                write_blob(&file_path, std::io::sink(), object_format)?
             };
             println!("{}", hash);
         }
+        Command::Log { commit, format } => {
+            let dot = format.as_deref() == Some("dot");
+            let object_format = read_object_format()?;
+            let commit = resolve_hash(&commit, object_format)?;
+
+            let mut visited = std::collections::HashSet::new();
+            let mut queue = std::collections::BinaryHeap::new();
+            queue.push(QueuedCommit::load(commit, object_format)?);
+
+            if dot {
+                println!("digraph {{");
+            }
+
+            while let Some(QueuedCommit { hash, commit, .. }) = queue.pop() {
+                if !visited.insert(hash.clone()) {
+                    continue;
+                }
+
+                if dot {
+                    let label = commit.message.lines().next().unwrap_or("").replace('"', "\\\"");
+                    println!("  \"{hash}\" [label=\"{label}\"]");
+                    for parent in &commit.parents {
+                        println!("  \"{hash}\" -> \"{parent}\"");
+                    }
+                } else {
+                    println!("commit {hash}");
+                    println!("Author: {}", commit.author);
+                    for line in commit.message.lines() {
+                        println!("    {line}");
+                    }
+                    println!();
+                }
+
+                for parent in commit.parents {
+                    if !visited.contains(&parent) {
+                        queue.push(QueuedCommit::load(parent, object_format)?);
+                    }
+                }
+            }
+
+            if dot {
+                println!("}}");
+            }
+        }
+        Command::UnpackObjects { pack_path } => {
+            let object_format = read_object_format()?;
+            let pack = fs::read(&pack_path)
+                .with_context(|| format!("failed to read pack file {}", pack_path.display()))?;
+
+            let hashes = packfile::unpack(
+                &pack,
+                object_format,
+                |kind, body| write_loose_object(kind, body, object_format),
+                |hash| {
+                    let (kind, body) = read_object(hash, object_format)?;
+                    Ok((kind.name().to_string(), body))
+                },
+            )?;
+
+            for hash in hashes {
+                println!("{hash}");
+            }
+        }
+        Command::CommitTree {
+            tree,
+            parents,
+            message,
+            sign,
+            signing_key,
+        } => {
+            let object_format = read_object_format()?;
+            let tree = resolve_hash(&tree, object_format)?;
+            let parents = parents
+                .iter()
+                .map(|parent| resolve_hash(parent, object_format))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let timestamp = now_unix()?;
+            let (author_name, author_email) = author_identity()?;
+            let (committer_name, committer_email) = committer_identity()?;
+
+            let mut header = format!("tree {tree}\n");
+            for parent in &parents {
+                header += &format!("parent {parent}\n");
+            }
+            header += &format!(
+                "author {}\n",
+                identity_line(&author_name, &author_email, timestamp)
+            );
+            header += &format!(
+                "committer {}\n",
+                identity_line(&committer_name, &committer_email, timestamp)
+            );
+
+            let mut message = message;
+            if !message.ends_with('\n') {
+                message.push('\n');
+            }
+
+            let commit_body = if sign {
+                let signing_key =
+                    signing_key.context("--sign requires --signing-key <path>")?;
+                let unsigned = format!("{header}\n{message}");
+                let signature = sign_ssh(&unsigned, &signing_key)?;
+                format!(
+                    "{header}gpgsig {}\n\n{message}",
+                    fold_header_value(signature.trim_end())
+                )
+            } else {
+                format!("{header}\n{message}")
+            };
+
+            let hash = write_loose_object("commit", commit_body.as_bytes(), object_format)?;
+            println!("{hash}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve an (possibly abbreviated) object hash prefix to the single full
+/// hash it names, the way `git cat-file -p 3b18e` resolves a short hash.
+///
+/// Git's loose objects are stored as `.git/objects/<first 2 chars>/<rest>`,
+/// so any prefix of at least 4 characters can be resolved by listing the
+/// fanout directory for the first two characters and matching the rest
+/// against the remaining file names.
+fn resolve_hash(prefix: &str, format: ObjectFormat) -> anyhow::Result<String> {
+    if prefix.len() == format.hex_len() {
+        return Ok(prefix.to_string());
+    }
+
+    anyhow::ensure!(
+        prefix.len() >= 4,
+        "object hash prefix must be at least 4 characters long"
+    );
+
+    let (dir, rest) = prefix.split_at(2);
+    let dir_path = format!("{GIT_OBJECT_DIR}/{dir}");
+    let entries = fs::read_dir(&dir_path).with_context(|| format!("failed to read {dir_path}"))?;
+
+    let mut candidates = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("failed to read entry in {dir_path}"))?;
+        let name = entry.file_name();
+        let name = name.to_str().context("object file name is valid utf-8")?;
+        if name.starts_with(rest) {
+            candidates.push(format!("{dir}{name}"));
+        }
+    }
+
+    match candidates.as_slice() {
+        [] => anyhow::bail!("object {prefix} not found"),
+        [hash] => Ok(hash.clone()),
+        _ => anyhow::bail!(
+            "object {prefix} is ambiguous, candidates: {}",
+            candidates.join(", ")
+        ),
+    }
+}
+
+/// A temp file path inside `.git/objects` (not the CWD) so a final rename
+/// stays on the same filesystem and never clobbers another writer's temp
+/// file.
+fn new_tmp_object_path() -> String {
+    format!(
+        "{GIT_OBJECT_DIR}/tmp_obj_{}_{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("system clock is after the unix epoch")
+            .as_nanos()
+    )
+}
+
+/// Move a temp object file written by `new_tmp_object_path` into its
+/// fanout directory, creating that directory as needed, and mark the
+/// result read-only like git does. If an object with this hash already
+/// exists, the temp file is discarded instead - objects are
+/// content-addressed and immutable, so there's nothing left to write.
+fn finish_object(tmp_path: &str, hash: &str) -> anyhow::Result<()> {
+    let object_path = format!("{GIT_OBJECT_DIR}/{}/{}", &hash[..2], &hash[2..]);
+    if Path::new(&object_path).exists() {
+        fs::remove_file(tmp_path).context("failed to remove redundant temporary file")?;
+        return Ok(());
+    }
+
+    fs::create_dir_all(format!("{GIT_OBJECT_DIR}/{}", &hash[..2]))
+        .context("failed to create object fanout directory")?;
+    fs::rename(tmp_path, &object_path)
+        .context("failed to move temporary file to object directory")?;
+
+    let mut perms = fs::metadata(&object_path)
+        .context("failed to read permissions of written object")?
+        .permissions();
+    perms.set_readonly(true);
+    fs::set_permissions(&object_path, perms).context("failed to mark object file read-only")?;
+
+    Ok(())
+}
+
+/// Hash, compress and persist an object whose body is already fully in
+/// memory - unlike `hash-object`'s `write_blob`, which streams a file in,
+/// this is for bodies assembled by other commands (`unpack-objects`,
+/// `commit-tree`).
+fn write_loose_object(kind: &str, body: &[u8], format: ObjectFormat) -> anyhow::Result<String> {
+    let tmp_path = new_tmp_object_path();
+    let hash = {
+        let file = File::create(&tmp_path).context("failed to construct temporary object file")?;
+        let mut writer = HashWriter {
+            writer: ZlibEncoder::new(file, Compression::default()),
+            hasher: AnyHasher::new(format),
+        };
+        write!(writer, "{kind} {}\0", body.len())?;
+        writer.write_all(body)?;
+        let _ = writer.writer.finish()?;
+        writer.hasher.finalize_hex()
+    };
+    finish_object(&tmp_path, &hash)?;
+    Ok(hash)
+}
+
+/// Current time as seconds since the epoch, for `author`/`committer`
+/// lines.
+fn now_unix() -> anyhow::Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("system clock is before the unix epoch")?
+        .as_secs())
+}
+
+/// Format a commit's `author`/`committer` line: `Name <email> <unixtime> <tz>`.
+fn identity_line(name: &str, email: &str, timestamp: u64) -> String {
+    format!("{name} <{email}> {timestamp} +0000")
+}
+
+/// The author identity for a new commit, from `GIT_AUTHOR_NAME` /
+/// `GIT_AUTHOR_EMAIL`, the same environment variables real git reads.
+fn author_identity() -> anyhow::Result<(String, String)> {
+    let name = std::env::var("GIT_AUTHOR_NAME")
+        .context("GIT_AUTHOR_NAME must be set to author a commit")?;
+    let email = std::env::var("GIT_AUTHOR_EMAIL")
+        .context("GIT_AUTHOR_EMAIL must be set to author a commit")?;
+    Ok((name, email))
+}
+
+/// The committer identity for a new commit, from `GIT_COMMITTER_NAME` /
+/// `GIT_COMMITTER_EMAIL`, falling back to the author identity when unset -
+/// matching real git's behavior for a plain `commit-tree`.
+fn committer_identity() -> anyhow::Result<(String, String)> {
+    match (
+        std::env::var("GIT_COMMITTER_NAME"),
+        std::env::var("GIT_COMMITTER_EMAIL"),
+    ) {
+        (Ok(name), Ok(email)) => Ok((name, email)),
+        _ => author_identity(),
+    }
+}
+
+/// Fold a multi-line header value the way git wraps `gpgsig`: every line
+/// after the first is indented by one continuation space.
+fn fold_header_value(value: &str) -> String {
+    value.lines().collect::<Vec<_>>().join("\n ")
+}
+
+/// Produce an armored SSH signature (SSHSIG) over `buffer`, the way
+/// `git commit -S` does with `gpg.format=ssh`: shell out to
+/// `ssh-keygen -Y sign`, which frames the payload under the `"git"`
+/// namespace, hashes it with SHA-512, and signs the framed blob with
+/// `signing_key`.
+fn sign_ssh(buffer: &str, signing_key: &Path) -> anyhow::Result<String> {
+    let mut child = std::process::Command::new("ssh-keygen")
+        .arg("-Y")
+        .arg("sign")
+        .arg("-n")
+        .arg("git")
+        .arg("-f")
+        .arg(signing_key)
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("failed to spawn ssh-keygen for SSH commit signing")?;
+
+    child
+        .stdin
+        .take()
+        .context("ssh-keygen stdin was not piped")?
+        .write_all(buffer.as_bytes())
+        .context("failed to write commit buffer to ssh-keygen")?;
+
+    let output = child
+        .wait_with_output()
+        .context("failed to wait for ssh-keygen")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "ssh-keygen failed to sign the commit: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).context("ssh-keygen produced a non-utf8 signature")
+}
+
+/// Read and zlib-decompress an object by its full hash, returning its kind
+/// and body (everything after the `"<kind> <size>\0"` header).
+///
+/// The empty blob and empty tree are recognized by their well-known hash
+/// alone, without requiring a loose object on disk - git itself doesn't
+/// always bother writing them out.
+fn read_object(hash: &str, format: ObjectFormat) -> anyhow::Result<(Kind, Vec<u8>)> {
+    if hash == format.empty_blob() {
+        return Ok((Kind::Blob, Vec::new()));
+    }
+    if hash == format.empty_tree() {
+        return Ok((Kind::Tree, Vec::new()));
+    }
+
+    let file = File::open(format!("{GIT_OBJECT_DIR}/{}/{}", &hash[..2], &hash[2..]))
+        .context(format!("Failed to open {GIT_OBJECT_DIR}"))?;
+
+    let zlib = ZlibDecoder::new(file);
+    let mut zlib = BufReader::new(zlib);
+    let mut buf = Vec::new();
+    zlib.read_until(0, &mut buf)
+        .context(format!("Failed to read header from {GIT_OBJECT_DIR}"))?;
+    let header = CStr::from_bytes_until_nul(&buf)
+        .expect("there is only one nul and that is at the end - this should not fail");
+    let header = header.to_str().context("header is valid utf-8")?;
+
+    let Some((kind, size)) = header.split_once(' ') else {
+        anyhow::bail!(
+            "corrupted {GIT_OBJECT_DIR}! header doesn't start with a known known kind: '{header}'"
+        )
+    };
+
+    let kind = match kind {
+        "blob" => Kind::Blob,
+        "commit" => Kind::Commit,
+        "tree" => Kind::Tree,
+        "tag" => Kind::Tag,
+        _ => anyhow::bail!("kind {kind} is not implemented yet"),
+    };
+
+    let size = size
+        .parse::<u64>()
+        .context("failed to parse size: {size}")?;
+
+    let mut zlib = LimitReader {
+        reader: zlib,
+        limit: size as usize,
+    };
+
+    buf.clear();
+    let n =
+        std::io::copy(&mut zlib, &mut buf).context("write .git/objects file to stdout")?;
+    anyhow::ensure!(
+        n == size,
+        "{GIT_OBJECT_DIR} was not expected size (expected: {size} actual: {n}"
+    );
+
+    Ok((kind, buf))
+}
+
+/// A parsed commit object: the header lines `git commit-tree` understands,
+/// plus the free-form message that follows the blank line separator.
+struct Commit {
+    #[allow(dead_code)]
+    tree: String,
+    parents: Vec<String>,
+    author: String,
+    #[allow(dead_code)]
+    committer: String,
+    /// Seconds since the epoch, parsed out of the committer line's
+    /// trailing `<unixtime> <tz>`. Used to order `log` traversal.
+    committer_time: i64,
+    message: String,
+}
+
+/// Parse a commit object body: header lines of the form `key SP value`
+/// (`tree`, zero or more `parent`, `author`, `committer`) up to a blank
+/// line, followed by the commit message.
+fn parse_commit(buf: &[u8]) -> anyhow::Result<Commit> {
+    let body = std::str::from_utf8(buf).context("commit body is valid utf-8")?;
+
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+
+    let mut lines = body.lines();
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+
+        let (key, value) = line
+            .split_once(' ')
+            .context("corrupted commit: header line missing a value")?;
+
+        match key {
+            "tree" => tree = Some(value.to_string()),
+            "parent" => parents.push(value.to_string()),
+            "author" => author = Some(value.to_string()),
+            "committer" => committer = Some(value.to_string()),
+            _ => {} // unknown headers (e.g. `gpgsig`, `encoding`) are ignored
+        }
+    }
+
+    let message = lines.collect::<Vec<_>>().join("\n");
+
+    let committer = committer.context("corrupted commit: missing committer header")?;
+    let committer_time = committer
+        .rsplit(' ')
+        .nth(1)
+        .context("corrupted commit: committer header missing unix time")?
+        .parse::<i64>()
+        .context("corrupted commit: committer unix time is not an integer")?;
+
+    Ok(Commit {
+        tree: tree.context("corrupted commit: missing tree header")?,
+        parents,
+        author: author.context("corrupted commit: missing author header")?,
+        committer,
+        committer_time,
+        message,
+    })
+}
+
+/// An entry in `log`'s traversal queue, ordered by commit time so the
+/// newest commit (across all branches currently in flight) is visited
+/// next - the same order `git log` walks history in.
+struct QueuedCommit {
+    hash: String,
+    commit: Commit,
+}
+
+impl QueuedCommit {
+    fn load(hash: String, format: ObjectFormat) -> anyhow::Result<Self> {
+        let (kind, buf) = read_object(&hash, format)?;
+        anyhow::ensure!(
+            matches!(kind, Kind::Commit),
+            "object {hash} is not a commit"
+        );
+        let commit = parse_commit(&buf)?;
+        Ok(Self { hash, commit })
+    }
+}
+
+impl PartialEq for QueuedCommit {
+    fn eq(&self, other: &Self) -> bool {
+        self.commit.committer_time == other.commit.committer_time
+    }
+}
+
+impl Eq for QueuedCommit {}
+
+impl PartialOrd for QueuedCommit {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedCommit {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.commit.committer_time.cmp(&other.commit.committer_time)
+    }
+}
+
+/// Decode a tree object body and print one `<mode> <type> <hexsha>\t<name>`
+/// line per entry, the way `git cat-file -p` does for a tree.
+///
+/// A tree body is a back-to-back sequence of entries with no separators
+/// between them: an ASCII mode, a space, a NUL-terminated name, then the
+/// entry's raw (not hex-encoded) object hash bytes - 20 bytes for SHA-1,
+/// 32 for SHA-256.
+fn print_tree(mut entries: &[u8], format: ObjectFormat) -> anyhow::Result<()> {
+    while !entries.is_empty() {
+        let space = entries
+            .iter()
+            .position(|&b| b == b' ')
+            .context("corrupted tree entry: missing mode separator")?;
+        let mode = std::str::from_utf8(&entries[..space])
+            .context("tree entry mode is valid utf-8")?;
+        entries = &entries[space + 1..];
+
+        let nul = entries
+            .iter()
+            .position(|&b| b == 0)
+            .context("corrupted tree entry: missing name terminator")?;
+        let name =
+            std::str::from_utf8(&entries[..nul]).context("tree entry name is valid utf-8")?;
+        entries = &entries[nul + 1..];
+
+        anyhow::ensure!(
+            entries.len() >= format.raw_len(),
+            "corrupted tree entry: truncated object hash"
+        );
+        let (hash, rest) = entries.split_at(format.raw_len());
+        entries = rest;
+
+        let kind = match mode {
+            "40000" => "tree",
+            "100644" | "100755" => "blob",
+            "120000" => "blob",
+            "160000" => "commit",
+            _ => anyhow::bail!("unrecognized tree entry mode: {mode}"),
+        };
+
+        println!("{mode:0>6} {kind} {}\t{name}", hex::encode(hash));
     }
 
     Ok(())
@@ -203,7 +831,7 @@ where
 
 struct HashWriter<W> {
     writer: W,
-    hasher: Sha1,
+    hasher: AnyHasher,
 }
 
 impl<W> Write for HashWriter<W>
@@ -211,9 +839,8 @@ where
     W: Write,
 {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        use sha1::digest::Digest;
         let n = self.writer.write(buf)?;
-        Digest::update(&mut self.hasher, &buf[..n]);
+        self.hasher.update(&buf[..n]);
         Ok(n)
     }
 
@@ -0,0 +1,334 @@
+//! Parsing for git's pack format: the `PACK` header, the per-object
+//! type+size varint, zlib-deflated object content, and the two delta
+//! encodings (`OFS_DELTA`, `REF_DELTA`) used by `unpack-objects`.
+
+use crate::ObjectFormat;
+use anyhow::Context;
+use flate2::bufread::ZlibDecoder;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+const MAGIC: &[u8; 4] = b"PACK";
+const CHECKSUM_LEN: usize = 20;
+
+/// A callback that fetches an already-persisted object by hash, used to
+/// find `REF_DELTA` bases that live outside this pack.
+type LookupExisting<'a> = dyn Fn(&str) -> anyhow::Result<(String, Vec<u8>)> + 'a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RawType {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+    OfsDelta,
+    RefDelta,
+}
+
+impl RawType {
+    fn from_bits(bits: u8) -> anyhow::Result<Self> {
+        match bits {
+            1 => Ok(RawType::Commit),
+            2 => Ok(RawType::Tree),
+            3 => Ok(RawType::Blob),
+            4 => Ok(RawType::Tag),
+            6 => Ok(RawType::OfsDelta),
+            7 => Ok(RawType::RefDelta),
+            other => anyhow::bail!("unknown pack object type {other}"),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RawType::Commit => "commit",
+            RawType::Tree => "tree",
+            RawType::Blob => "blob",
+            RawType::Tag => "tag",
+            RawType::OfsDelta | RawType::RefDelta => {
+                unreachable!("delta entries don't have a direct type name")
+            }
+        }
+    }
+}
+
+/// One object as it appears in the pack: its inflated bytes (the object
+/// body for a base type, or the delta stream for a delta type) plus
+/// enough to find its base, if it has one.
+struct RawEntry {
+    offset: usize,
+    raw_type: RawType,
+    data: Vec<u8>,
+    base_offset: Option<usize>,
+    base_ref: Option<Vec<u8>>,
+}
+
+/// Read the 3-bit type and size out of a pack entry's variable-length
+/// header: the first byte holds the type in bits 4-6 and the low 4 size
+/// bits; the high bit marks a continuation byte, each contributing 7 more
+/// size bits, least-significant first.
+fn read_type_and_size(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<(u8, u64)> {
+    let mut byte = [0u8; 1];
+    cursor
+        .read_exact(&mut byte)
+        .context("truncated pack entry header")?;
+
+    let obj_type = (byte[0] >> 4) & 0x07;
+    let mut size = (byte[0] & 0x0F) as u64;
+    let mut shift = 4;
+    while byte[0] & 0x80 != 0 {
+        cursor
+            .read_exact(&mut byte)
+            .context("truncated pack entry header")?;
+        size |= ((byte[0] & 0x7F) as u64) << shift;
+        shift += 7;
+    }
+
+    Ok((obj_type, size))
+}
+
+/// Read an `OFS_DELTA` base offset: a varint with a different accumulation
+/// rule than the size varint above (each continuation adds 1 before
+/// shifting, so offsets have no redundant encodings).
+fn read_ofs_delta_offset(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<u64> {
+    let mut byte = [0u8; 1];
+    cursor
+        .read_exact(&mut byte)
+        .context("truncated ofs-delta offset")?;
+
+    let mut offset = (byte[0] & 0x7F) as u64;
+    while byte[0] & 0x80 != 0 {
+        cursor
+            .read_exact(&mut byte)
+            .context("truncated ofs-delta offset")?;
+        offset = ((offset + 1) << 7) | (byte[0] & 0x7F) as u64;
+    }
+
+    Ok(offset)
+}
+
+/// Parse every object entry out of a pack's body (every byte before the
+/// trailing checksum, starting with the 12-byte header, which is checked
+/// here).
+fn parse_entries(body: &[u8], format: ObjectFormat) -> anyhow::Result<Vec<RawEntry>> {
+    anyhow::ensure!(body.len() >= 12, "pack is too short to contain a header");
+    anyhow::ensure!(&body[..4] == MAGIC, "pack is missing the 'PACK' magic");
+    let version = u32::from_be_bytes(body[4..8].try_into().unwrap());
+    anyhow::ensure!(
+        version == 2 || version == 3,
+        "unsupported pack version {version}"
+    );
+    let object_count = u32::from_be_bytes(body[8..12].try_into().unwrap());
+
+    let mut cursor = Cursor::new(body);
+    cursor.set_position(12);
+
+    let mut entries = Vec::with_capacity(object_count as usize);
+    for _ in 0..object_count {
+        let entry_offset = cursor.position() as usize;
+        let (type_bits, _inflated_size) = read_type_and_size(&mut cursor)?;
+        let raw_type = RawType::from_bits(type_bits)?;
+
+        let (base_offset, base_ref) = match raw_type {
+            RawType::OfsDelta => {
+                let back = read_ofs_delta_offset(&mut cursor)?;
+                let base_offset = entry_offset
+                    .checked_sub(back as usize)
+                    .context("ofs-delta offset underflows the pack")?;
+                (Some(base_offset), None)
+            }
+            RawType::RefDelta => {
+                let mut id = vec![0u8; format.raw_len()];
+                cursor
+                    .read_exact(&mut id)
+                    .context("truncated ref-delta base id")?;
+                (None, Some(id))
+            }
+            RawType::Commit | RawType::Tree | RawType::Blob | RawType::Tag => (None, None),
+        };
+
+        let mut decoder = ZlibDecoder::new(&mut cursor);
+        let mut data = Vec::new();
+        decoder
+            .read_to_end(&mut data)
+            .context("failed to inflate pack entry")?;
+
+        entries.push(RawEntry {
+            offset: entry_offset,
+            raw_type,
+            data,
+            base_offset,
+            base_ref,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Read a delta-stream varint: 7 bits per byte, least-significant first,
+/// continuing while the high bit is set.
+fn read_delta_varint(delta: &[u8], pos: &mut usize) -> anyhow::Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        anyhow::ensure!(*pos < delta.len(), "truncated delta varint");
+        let byte = delta[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+/// Reconstruct a target object from a base object and a git delta stream:
+/// a source size, a target size, then a run of copy (high bit set - copy
+/// `size` bytes from `offset` in the base) and insert (low 7 bits give a
+/// literal length to emit) instructions.
+fn apply_delta(base: &[u8], delta: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut pos = 0;
+    let source_size = read_delta_varint(delta, &mut pos)?;
+    anyhow::ensure!(
+        source_size as usize == base.len(),
+        "delta base size mismatch"
+    );
+    let target_size = read_delta_varint(delta, &mut pos)?;
+
+    let mut out = Vec::with_capacity(target_size as usize);
+    while pos < delta.len() {
+        let opcode = delta[pos];
+        pos += 1;
+
+        if opcode & 0x80 != 0 {
+            let mut offset = 0u32;
+            for bit in 0..4 {
+                if opcode & (1 << bit) != 0 {
+                    anyhow::ensure!(pos < delta.len(), "truncated copy instruction offset");
+                    offset |= (delta[pos] as u32) << (bit * 8);
+                    pos += 1;
+                }
+            }
+
+            let mut size = 0u32;
+            for bit in 0..3 {
+                if opcode & (1 << (4 + bit)) != 0 {
+                    anyhow::ensure!(pos < delta.len(), "truncated copy instruction size");
+                    size |= (delta[pos] as u32) << (bit * 8);
+                    pos += 1;
+                }
+            }
+            let size = if size == 0 { 0x10000 } else { size };
+
+            let (offset, size) = (offset as usize, size as usize);
+            anyhow::ensure!(
+                offset.checked_add(size).is_some_and(|end| end <= base.len()),
+                "copy instruction reads past the base object"
+            );
+            out.extend_from_slice(&base[offset..offset + size]);
+        } else if opcode != 0 {
+            let len = opcode as usize;
+            anyhow::ensure!(pos + len <= delta.len(), "truncated insert instruction");
+            out.extend_from_slice(&delta[pos..pos + len]);
+            pos += len;
+        } else {
+            anyhow::bail!("invalid delta opcode 0");
+        }
+    }
+
+    anyhow::ensure!(
+        out.len() as u64 == target_size,
+        "delta produced unexpected target size"
+    );
+    Ok(out)
+}
+
+/// Resolve one entry to its final `(kind, body)`, applying deltas
+/// recursively and memoizing results so a base shared by many deltas is
+/// only decoded once.
+fn resolve(
+    index: usize,
+    entries: &[RawEntry],
+    offset_index: &HashMap<usize, usize>,
+    cache: &mut HashMap<usize, (String, Vec<u8>)>,
+    lookup_existing: &LookupExisting,
+) -> anyhow::Result<(String, Vec<u8>)> {
+    if let Some(done) = cache.get(&index) {
+        return Ok(done.clone());
+    }
+
+    let entry = &entries[index];
+    let result = match entry.raw_type {
+        RawType::Commit | RawType::Tree | RawType::Blob | RawType::Tag => {
+            (entry.raw_type.name().to_string(), entry.data.clone())
+        }
+        RawType::OfsDelta => {
+            let base_offset = entry
+                .base_offset
+                .context("ofs-delta entry missing its base offset")?;
+            let base_index = *offset_index
+                .get(&base_offset)
+                .context("ofs-delta base offset doesn't point at an entry in this pack")?;
+            let (kind, base) = resolve(base_index, entries, offset_index, cache, lookup_existing)?;
+            (kind, apply_delta(&base, &entry.data)?)
+        }
+        RawType::RefDelta => {
+            let base_ref = entry
+                .base_ref
+                .as_ref()
+                .context("ref-delta entry missing its base id")?;
+            let base_hex = hex::encode(base_ref);
+            let (kind, base) = lookup_existing(&base_hex)
+                .with_context(|| format!("ref-delta base object {base_hex} not found"))?;
+            (kind, apply_delta(&base, &entry.data)?)
+        }
+    };
+
+    cache.insert(index, result.clone());
+    Ok(result)
+}
+
+/// Explode a packfile into loose objects: verify the trailing 20-byte
+/// SHA-1 checksum, parse every entry, resolve deltas against either an
+/// earlier entry in the same pack (`OFS_DELTA`) or an object already on
+/// disk (`REF_DELTA`, via `lookup_existing`), then persist each resolved
+/// object through `write_object` (`(kind, body) -> hash`, the same
+/// hashing path `hash-object -w` uses). Returns the hash of every object
+/// written, in pack order.
+pub fn unpack(
+    pack: &[u8],
+    format: ObjectFormat,
+    mut write_object: impl FnMut(&str, &[u8]) -> anyhow::Result<String>,
+    lookup_existing: impl Fn(&str) -> anyhow::Result<(String, Vec<u8>)>,
+) -> anyhow::Result<Vec<String>> {
+    anyhow::ensure!(
+        pack.len() >= CHECKSUM_LEN,
+        "pack is too short to contain a trailing checksum"
+    );
+    let (body, trailer) = pack.split_at(pack.len() - CHECKSUM_LEN);
+
+    let mut hasher = Sha1::new();
+    Digest::update(&mut hasher, body);
+    let computed = hasher.finalize();
+    anyhow::ensure!(
+        computed.as_slice() == trailer,
+        "pack checksum mismatch: the trailing SHA-1 doesn't match the pack contents"
+    );
+
+    let entries = parse_entries(body, format)?;
+    let offset_index: HashMap<usize, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (entry.offset, i))
+        .collect();
+
+    let mut cache = HashMap::new();
+    let mut hashes = Vec::with_capacity(entries.len());
+    for index in 0..entries.len() {
+        let (kind, body) = resolve(index, &entries, &offset_index, &mut cache, &lookup_existing)?;
+        hashes.push(write_object(&kind, &body)?);
+    }
+
+    Ok(hashes)
+}